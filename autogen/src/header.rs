@@ -117,6 +117,104 @@ fn gen_bit_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
     }
 }
 
+fn parse_version(version: Option<&str>) -> Option<(u8, u8)> {
+    let version = version?;
+    if version == "None" {
+        return None;
+    }
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Builds `Self::A | Self::B => &[...]` match arms from a requirement
+/// clause (capabilities or extensions) grouped by its raw grammar value.
+fn gen_requirement_arms<'a>(
+    clauses: &BTreeMap<&'a Vec<String>, Vec<proc_macro2::Ident>>,
+    to_token: impl Fn(&str) -> TokenStream,
+) -> Vec<TokenStream> {
+    clauses
+        .iter()
+        .filter(|(names, _)| !names.is_empty())
+        .map(|(names, symbols)| {
+            let items = names.iter().map(|n| to_token(n));
+            quote! { #(Self::#symbols)|* => &[#(#items),*], }
+        })
+        .collect()
+}
+
+/// Builds `Self::A | Self::B => Some((1, 3))` match arms for a
+/// `min_version`/`max_version` style query grouped by `(major, minor)`.
+fn gen_version_arms(
+    clauses: &BTreeMap<Option<(u8, u8)>, Vec<proc_macro2::Ident>>,
+) -> Vec<TokenStream> {
+    clauses
+        .iter()
+        .map(|(version, symbols)| {
+            let value = match version {
+                Some((major, minor)) => quote! { Some((#major, #minor)) },
+                None => quote! { None },
+            };
+            quote! { #(Self::#symbols)|* => #value, }
+        })
+        .collect()
+}
+
+/// Generates the `required_capabilities`/`required_extensions`/
+/// `min_version`/`max_version` query methods shared by `Op` and the
+/// value-enum operand kinds.
+fn gen_requirement_methods(
+    kind: &proc_macro2::Ident,
+    capability_clauses: &BTreeMap<&Vec<String>, Vec<proc_macro2::Ident>>,
+    extension_clauses: &BTreeMap<&Vec<String>, Vec<proc_macro2::Ident>>,
+    version_clauses: &BTreeMap<Option<(u8, u8)>, Vec<proc_macro2::Ident>>,
+    last_version_clauses: &BTreeMap<Option<(u8, u8)>, Vec<proc_macro2::Ident>>,
+) -> TokenStream {
+    let capability_arms = gen_requirement_arms(capability_clauses, |name| {
+        let cap = as_ident(name);
+        quote! { Capability::#cap }
+    });
+    let extension_arms = gen_requirement_arms(extension_clauses, |name| quote! { #name });
+    let min_version_arms = gen_version_arms(version_clauses);
+    let max_version_arms = gen_version_arms(last_version_clauses);
+
+    quote! {
+        impl #kind {
+            /// Returns the capabilities required to use this, if any.
+            pub fn required_capabilities(self) -> &'static [Capability] {
+                match self {
+                    #(#capability_arms)*
+                    _ => &[],
+                }
+            }
+
+            /// Returns the extensions required to use this, if any.
+            pub fn required_extensions(self) -> &'static [&'static str] {
+                match self {
+                    #(#extension_arms)*
+                    _ => &[],
+                }
+            }
+
+            /// Returns the earliest core SPIR-V version this is defined in,
+            /// or `None` if it is not tied to a specific version (e.g. it
+            /// only exists via an extension).
+            pub fn min_version(self) -> Option<(u8, u8)> {
+                match self {
+                    #(#min_version_arms)*
+                }
+            }
+
+            /// Returns the last core SPIR-V version this is defined in,
+            /// or `None` if it has not been removed/superseded.
+            pub fn max_version(self) -> Option<(u8, u8)> {
+                match self {
+                    #(#max_version_arms)*
+                }
+            }
+        }
+    }
+}
+
 fn gen_value_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
     let kind = as_ident(&grammar.kind);
 
@@ -128,8 +226,10 @@ fn gen_value_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
     let mut aliases = vec![];
     let mut capability_clauses = BTreeMap::new();
     let mut extension_clauses = BTreeMap::new();
+    let mut version_clauses = BTreeMap::new();
+    let mut last_version_clauses = BTreeMap::new();
     let mut operand_clauses = BTreeMap::new();
-    let mut from_str_impl = vec![];
+    let mut from_str_entries = vec![];
     for e in &grammar.enumerants {
         if let Some(discriminator) = seen_discriminator.get(&e.value) {
             let name_str = &e.symbol;
@@ -137,7 +237,7 @@ fn gen_value_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
             aliases.push(quote! {
                 pub const #symbol: Self = Self::#discriminator;
             });
-            from_str_impl.push(quote! { #name_str => Ok(Self::#discriminator), });
+            from_str_entries.push((name_str.clone(), discriminator.clone()));
         } else {
             // Special case for Dim. Its enumerants can start with a digit.
             // So prefix with the kind name here.
@@ -153,7 +253,7 @@ fn gen_value_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
             seen_discriminator.insert(e.value, name.clone());
             enumerants.push(quote! { #name = #number });
             from_prim_list.push(quote! { #number => Self::#name });
-            from_str_impl.push(quote! { #name_str => Ok(Self::#name), });
+            from_str_entries.push((name_str.clone(), name.clone()));
 
             capability_clauses
                 .entry(&e.capabilities)
@@ -165,6 +265,16 @@ fn gen_value_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
                 .or_insert_with(Vec::new)
                 .push(name.clone());
 
+            version_clauses
+                .entry(parse_version(e.version.as_deref()))
+                .or_insert_with(Vec::new)
+                .push(name.clone());
+
+            last_version_clauses
+                .entry(parse_version(e.last_version.as_deref()))
+                .or_insert_with(Vec::new)
+                .push(name.clone());
+
             operand_clauses
                 .entry(name.clone())
                 .or_insert_with(Vec::new)
@@ -191,6 +301,14 @@ fn gen_value_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
     let attribute = value_enum_attribute();
 
     let from_prim_impl = from_primitive_impl(&from_prim_list, &kind);
+    let requirement_methods = gen_requirement_methods(
+        &kind,
+        &capability_clauses,
+        &extension_clauses,
+        &version_clauses,
+        &last_version_clauses,
+    );
+    let from_str_impl = gen_from_str_impl(&kind, &from_str_entries);
 
     quote! {
         #[doc = #comment]
@@ -207,14 +325,33 @@ fn gen_value_enum_operand_kind(grammar: &structs::OperandKind) -> TokenStream {
 
         #from_prim_impl
 
+        #requirement_methods
+
+        #from_str_impl
+    }
+}
+
+/// Builds a `core::str::FromStr` impl for `kind` backed by a `phf::Map`
+/// from symbol name to discriminant, built at generation time instead of
+/// emitting a linear string `match`.
+fn gen_from_str_impl(kind: &proc_macro2::Ident, entries: &[(String, proc_macro2::Ident)]) -> TokenStream {
+    let mut builder = phf_codegen::Map::new();
+    for (name, variant) in entries {
+        builder.entry(name.as_str(), &format!("{}::{}", kind, variant));
+    }
+    let map_literal: TokenStream = builder
+        .build()
+        .to_string()
+        .parse()
+        .expect("phf_codegen produced invalid Rust");
+
+    quote! {
         impl core::str::FromStr for #kind {
             type Err = ();
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
-                match s {
-                    #(#from_str_impl)*
-                    _ => Err(()),
-                }
+                static MAP: phf::Map<&'static str, #kind> = #map_literal;
+                MAP.get(s).copied().ok_or(())
             }
         }
     }
@@ -231,6 +368,135 @@ fn gen_operand_kind(grammar: &structs::OperandKind) -> Option<TokenStream> {
     }
 }
 
+/// The opcodes legal as the first literal operand of `OpSpecConstantOp`
+/// (i.e. the opcode the constant folds to once specialized). This is the
+/// fixed subset the spec calls out: arithmetic, shift, bitwise, logical,
+/// comparison, conversion, composite, select and access-chain
+/// instructions -- it is not derivable from any per-opcode
+/// grammar field, so it is hardcoded here like other generators do.
+const SPEC_CONSTANT_OP_OPCODES: &[&str] = &[
+    "SConvert",
+    "UConvert",
+    "FConvert",
+    "ConvertSToF",
+    "ConvertUToF",
+    "ConvertFToS",
+    "ConvertFToU",
+    "ConvertPtrToU",
+    "ConvertUToPtr",
+    "GenericCastToPtr",
+    "PtrCastToGeneric",
+    "Bitcast",
+    "QuantizeToF16",
+    "SNegate",
+    "Not",
+    "IAdd",
+    "ISub",
+    "IMul",
+    "UDiv",
+    "SDiv",
+    "UMod",
+    "SRem",
+    "SMod",
+    "ShiftRightLogical",
+    "ShiftRightArithmetic",
+    "ShiftLeftLogical",
+    "BitwiseOr",
+    "BitwiseXor",
+    "BitwiseAnd",
+    "FNegate",
+    "FAdd",
+    "FSub",
+    "FMul",
+    "FDiv",
+    "FRem",
+    "FMod",
+    "VectorShuffle",
+    "CompositeExtract",
+    "CompositeInsert",
+    "LogicalOr",
+    "LogicalAnd",
+    "LogicalNot",
+    "LogicalEqual",
+    "LogicalNotEqual",
+    "Select",
+    "IEqual",
+    "INotEqual",
+    "ULessThan",
+    "SLessThan",
+    "UGreaterThan",
+    "SGreaterThan",
+    "ULessThanEqual",
+    "SLessThanEqual",
+    "UGreaterThanEqual",
+    "SGreaterThanEqual",
+    "AccessChain",
+    "InBoundsAccessChain",
+    "PtrAccessChain",
+    "InBoundsPtrAccessChain",
+];
+
+/// Generates `Op::is_valid_spec_constant_op` from `SPEC_CONSTANT_OP_OPCODES`.
+fn gen_spec_constant_op_method() -> TokenStream {
+    let opcodes = SPEC_CONSTANT_OP_OPCODES.iter().map(|name| as_ident(name));
+
+    quote! {
+        impl Op {
+            /// Returns whether this opcode is allowed as the wrapped
+            /// opcode of an `OpSpecConstantOp`.
+            pub fn is_valid_spec_constant_op(self) -> bool {
+                matches!(self, #(Op::#opcodes)|*)
+            }
+        }
+    }
+}
+
+/// Generates `Op::logical_operands`/`has_result`/`has_result_type` from
+/// the per-opcode operand layout collected while walking the grammar.
+fn gen_operand_layout_methods(
+    operand_layouts: &BTreeMap<proc_macro2::Ident, Vec<TokenStream>>,
+    has_result_opcodes: &[proc_macro2::Ident],
+    has_result_type_opcodes: &[proc_macro2::Ident],
+) -> TokenStream {
+    let layout_arms = operand_layouts.iter().map(|(opname, operands)| {
+        quote! { Op::#opname => &[#(#operands),*], }
+    });
+
+    let has_result_body = if has_result_opcodes.is_empty() {
+        quote! { false }
+    } else {
+        quote! { matches!(self, #(Op::#has_result_opcodes)|*) }
+    };
+    let has_result_type_body = if has_result_type_opcodes.is_empty() {
+        quote! { false }
+    } else {
+        quote! { matches!(self, #(Op::#has_result_type_opcodes)|*) }
+    };
+
+    quote! {
+        impl Op {
+            /// Returns the logical operand layout for this opcode, in the
+            /// order they appear after the opcode word (and after
+            /// `IdResultType`/`IdResult`, if present).
+            pub fn logical_operands(self) -> &'static [LogicalOperand] {
+                match self {
+                    #(#layout_arms)*
+                }
+            }
+
+            /// Returns whether this opcode produces a result id.
+            pub fn has_result(self) -> bool {
+                #has_result_body
+            }
+
+            /// Returns whether this opcode produces a result type id.
+            pub fn has_result_type(self) -> bool {
+                #has_result_type_body
+            }
+        }
+    }
+}
+
 /// Returns the generated SPIR-V header.
 pub fn gen_spirv_header(grammar: &structs::Grammar) -> TokenStream {
     // constants and types.
@@ -252,6 +518,14 @@ pub fn gen_spirv_header(grammar: &structs::Grammar) -> TokenStream {
     let mut opcodes = vec![];
     let mut aliases = vec![];
     let mut from_prim_list = vec![];
+    let mut from_str_entries = vec![];
+    let mut capability_clauses = BTreeMap::new();
+    let mut extension_clauses = BTreeMap::new();
+    let mut version_clauses = BTreeMap::new();
+    let mut last_version_clauses = BTreeMap::new();
+    let mut operand_layouts = BTreeMap::new();
+    let mut has_result_opcodes = vec![];
+    let mut has_result_type_opcodes = vec![];
 
     // Get the instruction table.
     for inst in &grammar.instructions {
@@ -260,19 +534,83 @@ pub fn gen_spirv_header(grammar: &structs::Grammar) -> TokenStream {
         let opcode = inst.opcode;
         if let Some(discriminator) = seen_discriminator.get(&opcode) {
             aliases.push(quote! { pub const #opname : Op = Op::#discriminator; });
+            from_str_entries.push((inst.opname.clone(), discriminator.clone()));
         } else {
             opcodes.push(quote! { #opname = #opcode });
             from_prim_list.push(quote! { #opcode => Op::#opname });
+            from_str_entries.push((inst.opname.clone(), opname.clone()));
             seen_discriminator.insert(opcode, opname.clone());
+
+            capability_clauses
+                .entry(&inst.capabilities)
+                .or_insert_with(Vec::new)
+                .push(opname.clone());
+
+            extension_clauses
+                .entry(&inst.extensions)
+                .or_insert_with(Vec::new)
+                .push(opname.clone());
+
+            version_clauses
+                .entry(parse_version(inst.version.as_deref()))
+                .or_insert_with(Vec::new)
+                .push(opname.clone());
+
+            last_version_clauses
+                .entry(parse_version(inst.last_version.as_deref()))
+                .or_insert_with(Vec::new)
+                .push(opname.clone());
+
+            if inst.operands.iter().any(|op| op.kind == "IdResult") {
+                has_result_opcodes.push(opname.clone());
+            }
+            if inst.operands.iter().any(|op| op.kind == "IdResultType") {
+                has_result_type_opcodes.push(opname.clone());
+            }
+
+            let operands = inst
+                .operands
+                .iter()
+                .filter(|op| op.kind != "IdResult" && op.kind != "IdResultType")
+                .map(|op| {
+                    let kind = as_ident(&op.kind);
+
+                    let quant = match op.quantifier {
+                        structs::Quantifier::One => quote! { OperandQuantifier::One },
+                        structs::Quantifier::ZeroOrOne => quote! { OperandQuantifier::ZeroOrOne },
+                        structs::Quantifier::ZeroOrMore => quote! { OperandQuantifier::ZeroOrMore },
+                    };
+
+                    quote! {
+                        LogicalOperand {
+                            kind: OperandKind::#kind,
+                            quantifier: #quant
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            operand_layouts.insert(opname.clone(), operands);
         }
     }
 
     let comment = format!("SPIR-V {} opcodes", get_spec_link("instructions"));
     let attribute = value_enum_attribute();
-    let from_prim_impl = from_primitive_impl(&from_prim_list, &as_ident("Op"));
+    let op_ident = as_ident("Op");
+    let from_prim_impl = from_primitive_impl(&from_prim_list, &op_ident);
+    let requirement_methods = gen_requirement_methods(
+        &op_ident,
+        &capability_clauses,
+        &extension_clauses,
+        &version_clauses,
+        &last_version_clauses,
+    );
+    let spec_constant_op_method = gen_spec_constant_op_method();
+    let from_str_impl = gen_from_str_impl(&op_ident, &from_str_entries);
+    let operand_layout_methods =
+        gen_operand_layout_methods(&operand_layouts, &has_result_opcodes, &has_result_type_opcodes);
 
     quote! {
-        //pub use crate::grammar::{OperandKind, OperandQuantifier, LogicalOperand};
+        pub use crate::grammar::{OperandKind, OperandQuantifier, LogicalOperand};
         pub type Word = u32;
         pub const MAGIC_NUMBER: u32 = #magic_number;
         pub const MAJOR_VERSION: u8 = #major_version;
@@ -295,6 +633,14 @@ pub fn gen_spirv_header(grammar: &structs::Grammar) -> TokenStream {
         }
 
         #from_prim_impl
+
+        #requirement_methods
+
+        #spec_constant_op_method
+
+        #from_str_impl
+
+        #operand_layout_methods
     }
 }
 
@@ -333,3 +679,78 @@ pub fn gen_opcodes(op: &str, grammar: &structs::ExtInstSetGrammar, comment: &str
         #from_prim_impl
     }
 }
+
+/// Options controlling how a generator's `TokenStream` is rendered to
+/// source text.
+pub struct GenOptions {
+    /// Pipe the generated source through `rustfmt` so the committed file
+    /// is readable instead of a single long line per item.
+    pub run_rustfmt: bool,
+    /// Path to the `rustfmt` binary to use. Defaults to `rustfmt` on
+    /// `PATH` when `None`.
+    pub rustfmt_path: Option<std::path::PathBuf>,
+}
+
+impl Default for GenOptions {
+    fn default() -> Self {
+        GenOptions {
+            run_rustfmt: false,
+            rustfmt_path: None,
+        }
+    }
+}
+
+/// Renders `tokens` to source text, optionally piping it through
+/// `rustfmt` per `options`. Falls back to the unformatted text if
+/// `run_rustfmt` is set but `rustfmt` can't be found or fails to run.
+pub fn format_source(tokens: TokenStream, options: &GenOptions) -> String {
+    let raw = tokens.to_string();
+    if !options.run_rustfmt {
+        return raw;
+    }
+    run_rustfmt(&raw, options.rustfmt_path.as_deref()).unwrap_or(raw)
+}
+
+/// Spawns `rustfmt`, feeds it `source` on stdin, and returns its
+/// formatted stdout. Returns `None` on any failure to spawn/run/parse,
+/// so callers can fall back to the unformatted source.
+fn run_rustfmt(source: &str, rustfmt_path: Option<&std::path::Path>) -> Option<String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let rustfmt = rustfmt_path
+        .map(std::path::Path::to_path_buf)
+        .unwrap_or_else(|| std::path::PathBuf::from("rustfmt"));
+
+    let mut child = Command::new(rustfmt)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    child.stdin.take()?.write_all(source.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Generates the SPIR-V header source, formatting it per `options`. This
+/// is the entry point generator binaries should call instead of
+/// stringifying `gen_spirv_header`'s `TokenStream` directly.
+pub fn gen_spirv_header_source(grammar: &structs::Grammar, options: &GenOptions) -> String {
+    format_source(gen_spirv_header(grammar), options)
+}
+
+/// Generates an extended instruction set's opcodes source, formatting it
+/// per `options`.
+pub fn gen_opcodes_source(
+    op: &str,
+    grammar: &structs::ExtInstSetGrammar,
+    comment: &str,
+    options: &GenOptions,
+) -> String {
+    format_source(gen_opcodes(op, grammar, comment), options)
+}